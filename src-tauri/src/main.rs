@@ -4,33 +4,319 @@
   windows_subsystem = "windows"
 )]
 
-use tauri::{Manager, AppHandle, WindowBuilder, WindowUrl};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
 
+use serde::Deserialize;
+use tauri::{
+  http::{Request, Response, ResponseBuilder},
+  AppHandle, GlobalShortcutManager, Manager, WindowBuilder, WindowUrl,
+};
+
+/// How often the background autosave task nudges the frontend with a `save-reminder`.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Upper bound on the HTML payload accepted by `open_preview`. Webviews cap the
+/// length of a navigable URL (WebView2 and WebKit both balk well under 2 MiB), and a
+/// `data:` URL is still subject to that ceiling after percent-encoding — a preview
+/// past this size gets silently truncated rather than rendered, so reject it instead.
+const MAX_PREVIEW_HTML_BYTES: usize = 512 * 1024;
+
+/// A single-use chunk of bytes handed to the frontend via the `story://` protocol.
+///
+/// Entries are removed from `Context::resources` as soon as they're served, so a
+/// resource-id is only ever good for one request.
+struct ResourceBuffer {
+  bytes: Vec<u8>,
+  mime_type: String,
+}
+
+/// App-managed state backing the `story://` URI scheme and the autosave subsystem.
+#[derive(Default)]
+struct Context {
+  resources: Mutex<HashMap<String, ResourceBuffer>>,
+  current_document: Mutex<Option<PathBuf>>,
+}
+
+/// Serves a `story://<resource-id>` request from `Context::resources`, removing the
+/// entry so each URL is consumed exactly once.
+///
+/// Tauri v1 only serves custom schemes as `story://<resource-id>` on Linux/macOS; on
+/// Windows (WebView2) the same request arrives as `https://story.localhost/<resource-id>`.
+/// The native form is handled by slicing the raw URI string rather than going through
+/// `url::Url::host_str()` — the `url` crate lowercases the host component per the URL
+/// spec, which would corrupt mixed-case (e.g. base64/token-style) resource ids. The
+/// Windows form carries the id in the path instead, which `url` leaves case-intact.
+fn story_protocol(app: &AppHandle, request: &Request) -> Result<Response, Box<dyn std::error::Error>> {
+  let raw_uri = request.uri();
+  let resource_id = match raw_uri.strip_prefix("story://") {
+    Some(rest) => rest.trim_matches('/').to_string(),
+    None => {
+      let uri: url::Url = raw_uri.parse()?;
+      uri.path().trim_matches('/').to_string()
+    }
+  };
+  let resource_id = resource_id.as_str();
+
+  let context = app.state::<Context>();
+  let mut resources = context.resources.lock().unwrap();
+
+  match resources.remove(resource_id) {
+    Some(buffer) => Ok(
+      ResponseBuilder::new()
+        .mimetype(&buffer.mime_type)
+        .status(200)
+        .body(buffer.bytes)?,
+    ),
+    None => Ok(
+      ResponseBuilder::new()
+        .status(404)
+        .body("Resource not found".as_bytes().to_vec())?,
+    ),
+  }
+}
+
+/// Stores `bytes` under `key` so a subsequent `story://<key>` request can serve it.
+/// The caller mints `key` itself (e.g. a UUID) and builds the `story://<key>` URL to
+/// hand to the webview; the entry is consumed the first time that URL is fetched.
 #[tauri::command]
-async fn open_docs(app: AppHandle) -> Result<(), String> {
-  // Use "docs" as a unique label for the documentation window
-  if let Some(window) = app.get_window("docs") {
-      // If the window already exists, bring it to the front
-      window.set_focus().map_err(|e| e.to_string())?;
-  } else {
-      // Otherwise, create a new window
-      WindowBuilder::new(
-          &app,
-          "docs",
-          WindowUrl::App("README.html".into())
-      )
-      .title("MiStory Documentation")
-      .inner_size(900.0, 750.0)
-      .build()
+async fn register_resource(app: AppHandle, key: String, bytes: Vec<u8>, mime_type: String) -> Result<(), String> {
+  let context = app.state::<Context>();
+  context.resources.lock().unwrap().insert(key, ResourceBuffer { bytes, mime_type });
+  Ok(())
+}
+
+/// Options for [`open_window`], mirroring the subset of `WindowBuilder` the frontend
+/// is allowed to drive.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowOptions {
+  label: String,
+  url: String,
+  title: String,
+  #[serde(default = "default_width")]
+  width: f64,
+  #[serde(default = "default_height")]
+  height: f64,
+  #[serde(default = "default_true")]
+  resizable: bool,
+  #[serde(default)]
+  center: bool,
+  #[serde(default = "default_true")]
+  focused: bool,
+}
+
+fn default_width() -> f64 {
+  900.0
+}
+
+fn default_height() -> f64 {
+  750.0
+}
+
+fn default_true() -> bool {
+  true
+}
+
+#[tauri::command]
+async fn open_window(app: AppHandle, options: WindowOptions) -> Result<(), String> {
+  // If the window already exists, bring it to the front instead of building a new one
+  if let Some(window) = app.get_window(&options.label) {
+    window.set_focus().map_err(|e| e.to_string())?;
+    return Ok(());
+  }
+
+  let mut builder = WindowBuilder::new(&app, &options.label, WindowUrl::App(options.url.into()))
+    .title(&options.title)
+    .inner_size(options.width, options.height)
+    .resizable(options.resizable)
+    .focused(options.focused);
+
+  if options.center {
+    builder = builder.center();
+  }
+
+  builder.build().map_err(|e| e.to_string())?;
+  Ok(())
+}
+
+/// Atomically writes `contents` to `path` (write to a `.tmp` sibling, then rename) and
+/// records `path` as the current document, so the background autosave timer only nudges
+/// the frontend while a document is actually open.
+#[tauri::command]
+async fn save_document(app: AppHandle, path: String, contents: String) -> Result<(), String> {
+  let target = PathBuf::from(&path);
+  let tmp_path = target.with_extension("tmp");
+
+  std::fs::write(&tmp_path, contents).map_err(|e| e.to_string())?;
+  std::fs::rename(&tmp_path, &target).map_err(|e| e.to_string())?;
+
+  let context = app.state::<Context>();
+  *context.current_document.lock().unwrap() = Some(target);
+
+  if let Some(window) = app.get_window("main") {
+    window.emit("autosave-complete", &path).map_err(|e| e.to_string())?;
+  }
+  Ok(())
+}
+
+/// Carries out a shortcut's bound action against the main window. Unrecognized
+/// actions are forwarded as-is so the frontend can still listen for them directly.
+fn dispatch_shortcut_action(app: &AppHandle, action: &str) {
+  let window = match app.get_window("main") {
+    Some(window) => window,
+    None => return,
+  };
+
+  match action {
+    "hide-main-window" => {
+      let _ = window.hide();
+    }
+    "show-main-window" => {
+      let _ = window.unminimize();
+      let _ = window.set_focus();
+    }
+    _ => {
+      let _ = window.emit(action, ());
+    }
+  }
+}
+
+/// Registers `accelerator` to fire `action` when pressed, even while the app isn't
+/// focused. Re-registering an accelerator replaces its previous binding (tracked by
+/// the global shortcut manager itself via `is_registered`) so shortcut config can be
+/// changed at runtime without leaking stale registrations.
+#[tauri::command]
+async fn register_shortcut(app: AppHandle, accelerator: String, action: String) -> Result<(), String> {
+  let mut manager = app.global_shortcut_manager();
+
+  if manager.is_registered(&accelerator).unwrap_or(false) {
+    manager.unregister(&accelerator).map_err(|e| e.to_string())?;
+  }
+
+  let app_handle = app.clone();
+  manager
+    .register(&accelerator, move || dispatch_shortcut_action(&app_handle, &action))
+    .map_err(|e| e.to_string())?;
+
+  Ok(())
+}
+
+/// Opens (or refocuses) the "preview" window and points it at a data URL built from
+/// `html`. The payload is percent-encoded before embedding — a raw
+/// `data:text/html,<html>` breaks as soon as the markup contains `#`, `%`, quotes, or
+/// non-ASCII characters, since the webview parses the data URL before it ever loads.
+///
+/// `data:` URLs are still navigable URLs, so they're capped by the same ceiling as any
+/// other URL; markup longer than `MAX_PREVIEW_HTML_BYTES` (measured before
+/// percent-encoding) is rejected up front rather than handed to the webview, which
+/// would otherwise silently truncate it.
+#[tauri::command]
+async fn open_preview(app: AppHandle, html: String) -> Result<(), String> {
+  if html.len() > MAX_PREVIEW_HTML_BYTES {
+    return Err(format!(
+      "preview HTML is {} bytes, exceeding the {} byte limit",
+      html.len(),
+      MAX_PREVIEW_HTML_BYTES
+    ));
+  }
+
+  let data_url = format!("data:text/html,{}", urlencoding::encode(&html));
+
+  // Use "preview" as a unique label so repeated previews refresh one window
+  if let Some(window) = app.get_window("preview") {
+    // Window already exists — navigate it to the new preview and bring it to the
+    // front, rather than just refocusing whatever it was already showing.
+    window
+      .eval(&format!("window.location.replace({})", serde_json::to_string(&data_url).map_err(|e| e.to_string())?))
       .map_err(|e| e.to_string())?;
+    window.set_focus().map_err(|e| e.to_string())?;
+  } else {
+    // Otherwise, create a new window
+    WindowBuilder::new(
+      &app,
+      "preview",
+      WindowUrl::External(data_url.parse().map_err(|e: url::ParseError| e.to_string())?),
+    )
+    .title("Story Preview")
+    .inner_size(900.0, 750.0)
+    .build()
+    .map_err(|e| e.to_string())?;
   }
   Ok(())
 }
 
+#[tauri::command]
+async fn open_docs(app: AppHandle) -> Result<(), String> {
+  open_window(
+    app,
+    WindowOptions {
+      label: "docs".into(),
+      url: "README.html".into(),
+      title: "MiStory Documentation".into(),
+      width: default_width(),
+      height: default_height(),
+      resizable: true,
+      center: false,
+      focused: true,
+    },
+  )
+  .await
+}
+
 
 fn main() {
   tauri::Builder::default()
-    .invoke_handler(tauri::generate_handler![open_docs])
+    .manage(Context::default())
+    .register_uri_scheme_protocol("story", |app, request| story_protocol(app, request))
+    .invoke_handler(tauri::generate_handler![
+      open_docs,
+      open_window,
+      save_document,
+      register_shortcut,
+      open_preview,
+      register_resource
+    ])
+    .setup(|app| {
+      // Periodically remind the frontend to flush dirty state, independent of any
+      // invoke call it makes itself. Only nudge while a document is actually open —
+      // `Context::current_document` is set by `save_document` once the user has saved
+      // at least once.
+      let app_handle = app.handle();
+      tauri::async_runtime::spawn(async move {
+        loop {
+          tokio::time::sleep(AUTOSAVE_INTERVAL).await;
+
+          let context = app_handle.state::<Context>();
+          let has_open_document = context.current_document.lock().unwrap().is_some();
+
+          if has_open_document {
+            if let Some(window) = app_handle.get_window("main") {
+              let _ = window.emit("save-reminder", ());
+            }
+          }
+        }
+      });
+
+      // Default binding for quick-save; the frontend can rebind this later via the
+      // `register_shortcut` command. This is best-effort: a default binding must
+      // never be able to abort startup just because some other process already
+      // holds the accelerator, so log and carry on instead of propagating `?`.
+      //
+      // Note: cancel/dismiss-style keys like Escape are deliberately not bound here.
+      // A *global* shortcut fires even when the app isn't focused, so grabbing
+      // Escape would swallow it in every other application on the system. Handle
+      // Escape as an ordinary window-level keydown in the frontend instead.
+      let mut manager = app.handle().global_shortcut_manager();
+      let app_handle = app.handle();
+      if let Err(e) = manager.register("Ctrl+Shift+S", move || dispatch_shortcut_action(&app_handle, "quick-save")) {
+        eprintln!("failed to register default shortcut Ctrl+Shift+S: {}", e);
+      }
+
+      Ok(())
+    })
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }